@@ -1,17 +1,19 @@
 use btleplug::api::{
-    Central, Manager as _, Peripheral as _, ScanFilter,
+    Central, Manager as _, Peripheral as _, ScanFilter, WriteType,
 };
-use btleplug::platform::{Manager, Peripheral};
+use btleplug::platform::{Adapter, Manager, Peripheral, PeripheralId};
 use futures::StreamExt;
 use std::time::Duration;
 use tokio::sync::mpsc;
 use tracing::{debug, info, warn};
 use uuid::Uuid;
 
+use crate::config::MappingConfig;
+
 // Must match firmware UUIDs
 const RANGE_SERVICE_UUID: Uuid = Uuid::from_u128(0x00000001_7272_6e67_6669_6e6465720000);
 const RANGE_CHAR_UUID: Uuid = Uuid::from_u128(0x00000002_7272_6e67_6669_6e6465720000);
-const _RANGE_CONFIG_CHAR_UUID: Uuid = Uuid::from_u128(0x00000003_7272_6e67_6669_6e6465720000);
+const RANGE_CONFIG_CHAR_UUID: Uuid = Uuid::from_u128(0x00000003_7272_6e67_6669_6e6465720000);
 
 /// Events emitted by the BLE client
 #[derive(Debug)]
@@ -24,8 +26,22 @@ pub enum BleEvent {
     Connected,
 }
 
-/// Scan for and connect to the fancypants-nrf52 peripheral
-pub async fn find_device(device_name: &str, timeout_secs: u64) -> anyhow::Result<Peripheral> {
+/// Scan for the fancypants-nrf52 peripheral and return it unconnected
+/// (`run_ble_client` connects to it). If `cached_id` is
+/// given, a direct lookup by id is tried first (see `reconnect_by_id`), which
+/// is much faster than a full timed scan; a scan is only run if that fails (e.g.
+/// the adapter no longer knows the id) or no `cached_id` was given.
+///
+/// The scan itself is filtered to `RANGE_SERVICE_UUID`, so only rangefinders show
+/// up. If `device_address` is set, it's matched against each candidate's BLE
+/// address/id and takes priority over matching on `device_name`, which is fragile
+/// when multiple units advertise the same name.
+pub async fn find_device(
+    device_name: &str,
+    timeout_secs: u64,
+    device_address: Option<&str>,
+    cached_id: Option<&PeripheralId>,
+) -> anyhow::Result<Peripheral> {
     let manager = Manager::new().await?;
     let adapters = manager.adapters().await?;
 
@@ -36,8 +52,25 @@ pub async fn find_device(device_name: &str, timeout_secs: u64) -> anyhow::Result
     let adapter = &adapters[0];
     info!("Using adapter: {:?}", adapter.adapter_info().await?);
 
-    adapter.start_scan(ScanFilter::default()).await?;
-    info!("Scanning for '{}' ({}s timeout)...", device_name, timeout_secs);
+    if let Some(id) = cached_id {
+        match reconnect_by_id(adapter, id).await {
+            Ok(peripheral) => return Ok(peripheral),
+            Err(e) => warn!(
+                "Cached device id {:?} no longer usable ({:#}), falling back to scan",
+                id, e
+            ),
+        }
+    }
+
+    adapter
+        .start_scan(ScanFilter {
+            services: vec![RANGE_SERVICE_UUID],
+        })
+        .await?;
+    match device_address {
+        Some(addr) => info!("Scanning for address '{}' ({}s timeout)...", addr, timeout_secs),
+        None => info!("Scanning for '{}' ({}s timeout)...", device_name, timeout_secs),
+    }
 
     let deadline = tokio::time::Instant::now() + Duration::from_secs(timeout_secs);
 
@@ -50,7 +83,14 @@ pub async fn find_device(device_name: &str, timeout_secs: u64) -> anyhow::Result
         let peripherals = adapter.peripherals().await?;
         for p in peripherals {
             if let Some(props) = p.properties().await? {
-                if props.local_name.as_deref() == Some(device_name) {
+                let matches = match device_address {
+                    Some(addr) => {
+                        props.address.to_string().eq_ignore_ascii_case(addr)
+                            || p.id().to_string() == addr
+                    }
+                    None => props.local_name.as_deref() == Some(device_name),
+                };
+                if matches {
                     adapter.stop_scan().await?;
                     info!("Found device: {} ({:?})", device_name, p.id());
                     return Ok(p);
@@ -62,11 +102,23 @@ pub async fn find_device(device_name: &str, timeout_secs: u64) -> anyhow::Result
     }
 }
 
+/// Look up a previously discovered peripheral id directly, skipping the timed
+/// scan entirely, so the caller can reconnect to it. Fails if the adapter no
+/// longer knows about the id (e.g. it was forgotten since the last run); callers
+/// should fall back to a scan in that case.
+pub async fn reconnect_by_id(adapter: &Adapter, id: &PeripheralId) -> anyhow::Result<Peripheral> {
+    let peripheral = adapter.peripheral(id).await?;
+    info!("Reconnecting to cached device id {:?}", id);
+    Ok(peripheral)
+}
+
 /// Connect to the device, discover services, and subscribe to range notifications.
-/// Sends range updates through the provided channel.
+/// Sends range updates through the provided channel. If `push_config` is set, the
+/// config is written down to the firmware once, right after subscribing.
 pub async fn run_ble_client(
     peripheral: &Peripheral,
     tx: mpsc::UnboundedSender<BleEvent>,
+    push_config: Option<MappingConfig>,
 ) -> anyhow::Result<()> {
     // Connect
     peripheral.connect().await?;
@@ -90,6 +142,12 @@ pub async fn run_ble_client(
     peripheral.subscribe(&range_char).await?;
     info!("Subscribed to range notifications");
 
+    if let Some(config) = push_config {
+        if let Err(e) = write_config(peripheral, &config).await {
+            warn!("Failed to push config to firmware: {:#}", e);
+        }
+    }
+
     // Listen for notifications
     let mut events = peripheral.notifications().await?;
 
@@ -109,3 +167,29 @@ pub async fn run_ble_client(
     let _ = tx.send(BleEvent::Disconnected);
     Ok(())
 }
+
+/// Write the current mapping config down to the firmware over the RANGE_CONFIG
+/// characteristic, so sensor-side sampling/thresholds track the host-side mapping.
+///
+/// Payload layout (all little-endian): `min_range_mm: u16, max_range_mm: u16,
+/// sample_rate_hz: u8, deadzone_mm: u16`.
+pub async fn write_config(peripheral: &Peripheral, config: &MappingConfig) -> anyhow::Result<()> {
+    let chars = peripheral.characteristics();
+    let config_char = chars
+        .iter()
+        .find(|c| c.uuid == RANGE_CONFIG_CHAR_UUID)
+        .ok_or_else(|| anyhow::anyhow!("Range config characteristic not found"))?
+        .clone();
+
+    let mut payload = Vec::with_capacity(7);
+    payload.extend_from_slice(&config.min_range_mm.to_le_bytes());
+    payload.extend_from_slice(&config.max_range_mm.to_le_bytes());
+    payload.push(config.sample_rate_hz);
+    payload.extend_from_slice(&config.deadzone_mm.to_le_bytes());
+
+    peripheral
+        .write(&config_char, &payload, WriteType::WithResponse)
+        .await?;
+    info!("Pushed mapping config to firmware: {:?}", payload);
+    Ok(())
+}
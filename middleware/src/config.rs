@@ -1,13 +1,39 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::path::Path;
 
+/// Many-to-many rig config: any number of sensors can drive any number of toys,
+/// with `routes` saying which sensor feeds which toy(s).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
+    pub sensors: Vec<SensorConfig>,
+    pub toys: Vec<ToyConfig>,
+    pub routes: Vec<RouteConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SensorConfig {
+    /// Unique id for this sensor, referenced by `RouteConfig::sensor_id`
+    pub id: String,
     pub ble: BleConfig,
     pub mapping: MappingConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToyConfig {
+    /// Unique id for this toy, referenced by `RouteConfig::toy_ids`
+    pub id: String,
     pub buttplug: ButtplugConfig,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteConfig {
+    /// Which sensor drives this route
+    pub sensor_id: String,
+    /// Which toy(s) this sensor's mapped intensity is sent to
+    pub toy_ids: Vec<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BleConfig {
     /// BLE device name to scan for (must match CONFIG_BT_DEVICE_NAME in firmware)
@@ -16,6 +42,9 @@ pub struct BleConfig {
     pub scan_timeout_secs: u64,
     /// Reconnect delay on disconnect
     pub reconnect_delay_secs: u64,
+    /// Pin a specific unit by its BLE address/id instead of matching on `device_name`.
+    /// Takes priority over `device_name` when set.
+    pub device_address: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -34,6 +63,8 @@ pub struct MappingConfig {
     pub deadzone_mm: u16,
     /// Smoothing: exponential moving average factor (0.0 = no smoothing, 1.0 = max smoothing)
     pub smoothing: f64,
+    /// Sensor sample rate in Hz, pushed to the firmware when `--push-config` is used
+    pub sample_rate_hz: u8,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,32 +73,68 @@ pub struct ButtplugConfig {
     pub server_address: String,
     /// Device index to control (None = first available)
     pub device_index: Option<u32>,
-    /// Which actuator types to control
+    /// Which actuator types to control ("Vibrate", "Linear", "Rotate")
     pub actuator_types: Vec<String>,
+    /// Duration of one linear stroke, in milliseconds (only used for "Linear")
+    pub stroke_duration_ms: u32,
+    /// Rotation direction for "Rotate" actuators: true = clockwise
+    pub rotation_clockwise: bool,
+}
+
+impl MappingConfig {
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.min_intensity < 0.0 || self.min_intensity > 1.0 {
+            anyhow::bail!("min_intensity must be 0.0-1.0");
+        }
+        if self.max_intensity < 0.0 || self.max_intensity > 1.0 {
+            anyhow::bail!("max_intensity must be 0.0-1.0");
+        }
+        if self.min_range_mm >= self.max_range_mm {
+            anyhow::bail!("min_range_mm must be < max_range_mm");
+        }
+        if self.smoothing < 0.0 || self.smoothing > 1.0 {
+            anyhow::bail!("smoothing must be 0.0-1.0");
+        }
+        Ok(())
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Config {
-            ble: BleConfig {
-                device_name: "Rangefinder".to_string(),
-                scan_timeout_secs: 30,
-                reconnect_delay_secs: 5,
-            },
-            mapping: MappingConfig {
-                invert: true,  // closer = more intense
-                min_range_mm: 30,
-                max_range_mm: 300,
-                min_intensity: 0.0,
-                max_intensity: 1.0,
-                deadzone_mm: 500,
-                smoothing: 0.3,
-            },
-            buttplug: ButtplugConfig {
-                server_address: "ws://127.0.0.1:12345".to_string(),
-                device_index: None,
-                actuator_types: vec!["Vibrate".to_string()],
-            },
+            sensors: vec![SensorConfig {
+                id: "sensor0".to_string(),
+                ble: BleConfig {
+                    device_name: "Rangefinder".to_string(),
+                    scan_timeout_secs: 30,
+                    reconnect_delay_secs: 5,
+                    device_address: None,
+                },
+                mapping: MappingConfig {
+                    invert: true, // closer = more intense
+                    min_range_mm: 30,
+                    max_range_mm: 300,
+                    min_intensity: 0.0,
+                    max_intensity: 1.0,
+                    deadzone_mm: 500,
+                    smoothing: 0.3,
+                    sample_rate_hz: 20,
+                },
+            }],
+            toys: vec![ToyConfig {
+                id: "toy0".to_string(),
+                buttplug: ButtplugConfig {
+                    server_address: "ws://127.0.0.1:12345".to_string(),
+                    device_index: None,
+                    actuator_types: vec!["Vibrate".to_string()],
+                    stroke_duration_ms: 500,
+                    rotation_clockwise: true,
+                },
+            }],
+            routes: vec![RouteConfig {
+                sensor_id: "sensor0".to_string(),
+                toy_ids: vec!["toy0".to_string()],
+            }],
         }
     }
 }
@@ -88,18 +155,122 @@ impl Config {
     }
 
     fn validate(&self) -> anyhow::Result<()> {
-        if self.mapping.min_intensity < 0.0 || self.mapping.min_intensity > 1.0 {
-            anyhow::bail!("min_intensity must be 0.0-1.0");
+        if self.sensors.is_empty() {
+            anyhow::bail!("config must define at least one sensor");
         }
-        if self.mapping.max_intensity < 0.0 || self.mapping.max_intensity > 1.0 {
-            anyhow::bail!("max_intensity must be 0.0-1.0");
+        if self.toys.is_empty() {
+            anyhow::bail!("config must define at least one toy");
         }
-        if self.mapping.min_range_mm >= self.mapping.max_range_mm {
-            anyhow::bail!("min_range_mm must be < max_range_mm");
+
+        let sensor_ids: HashSet<&str> = self.sensors.iter().map(|s| s.id.as_str()).collect();
+        let toy_ids: HashSet<&str> = self.toys.iter().map(|t| t.id.as_str()).collect();
+        if sensor_ids.len() != self.sensors.len() {
+            anyhow::bail!("sensor ids must be unique");
         }
-        if self.mapping.smoothing < 0.0 || self.mapping.smoothing > 1.0 {
-            anyhow::bail!("smoothing must be 0.0-1.0");
+        if toy_ids.len() != self.toys.len() {
+            anyhow::bail!("toy ids must be unique");
+        }
+
+        for sensor in &self.sensors {
+            sensor.mapping.validate()?;
         }
+
+        for route in &self.routes {
+            if !sensor_ids.contains(route.sensor_id.as_str()) {
+                anyhow::bail!("route references unknown sensor id '{}'", route.sensor_id);
+            }
+            for toy_id in &route.toy_ids {
+                if !toy_ids.contains(toy_id.as_str()) {
+                    anyhow::bail!("route references unknown toy id '{}'", toy_id);
+                }
+            }
+        }
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minimal_config() -> Config {
+        Config {
+            sensors: vec![SensorConfig {
+                id: "sensor0".to_string(),
+                ble: BleConfig {
+                    device_name: "Rangefinder".to_string(),
+                    scan_timeout_secs: 30,
+                    reconnect_delay_secs: 5,
+                    device_address: None,
+                },
+                mapping: MappingConfig {
+                    invert: true,
+                    min_range_mm: 30,
+                    max_range_mm: 300,
+                    min_intensity: 0.0,
+                    max_intensity: 1.0,
+                    deadzone_mm: 500,
+                    smoothing: 0.3,
+                    sample_rate_hz: 20,
+                },
+            }],
+            toys: vec![ToyConfig {
+                id: "toy0".to_string(),
+                buttplug: ButtplugConfig {
+                    server_address: "ws://127.0.0.1:12345".to_string(),
+                    device_index: None,
+                    actuator_types: vec!["Vibrate".to_string()],
+                    stroke_duration_ms: 500,
+                    rotation_clockwise: true,
+                },
+            }],
+            routes: vec![RouteConfig {
+                sensor_id: "sensor0".to_string(),
+                toy_ids: vec!["toy0".to_string()],
+            }],
+        }
+    }
+
+    #[test]
+    fn default_config_is_valid() {
+        assert!(Config::default().validate().is_ok());
+    }
+
+    #[test]
+    fn minimal_config_is_valid() {
+        assert!(minimal_config().validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_duplicate_sensor_ids() {
+        let mut config = minimal_config();
+        let mut dup = config.sensors[0].clone();
+        dup.id = "sensor0".to_string();
+        config.sensors.push(dup);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn rejects_duplicate_toy_ids() {
+        let mut config = minimal_config();
+        let mut dup = config.toys[0].clone();
+        dup.id = "toy0".to_string();
+        config.toys.push(dup);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn rejects_route_with_unknown_sensor() {
+        let mut config = minimal_config();
+        config.routes[0].sensor_id = "no_such_sensor".to_string();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn rejects_route_with_unknown_toy() {
+        let mut config = minimal_config();
+        config.routes[0].toy_ids = vec!["no_such_toy".to_string()];
+        assert!(config.validate().is_err());
+    }
+}
@@ -0,0 +1,249 @@
+use crate::ble::{self, BleEvent};
+use crate::config::{Config, SensorConfig};
+use crate::mapper::RangeMapper;
+use crate::sim;
+use crate::toy::{NullToy, ToyController, ToyOutput};
+use crate::Args;
+use btleplug::api::Peripheral as _;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+/// A range/connection event tagged with the sensor it came from.
+struct TaggedEvent {
+    sensor_id: String,
+    event: BleEvent,
+}
+
+/// How long the dispatcher lets the first-connecting sensors wait for the rest
+/// to catch up before streaming starts anyway. Bounded so a single
+/// misconfigured/unreachable sensor can only delay the others, never wedge them.
+const STARTUP_GRACE: Duration = Duration::from_secs(10);
+
+/// Give every sensor up to `STARTUP_GRACE` to reach this point before any of
+/// them starts streaming, so BLE/Intiface connections land close together. A
+/// sensor that never gets here just lets the others go once the grace period
+/// elapses, instead of blocking them indefinitely like a hard barrier would.
+async fn wait_for_startup(ready_count: &AtomicUsize, total: usize) {
+    ready_count.fetch_add(1, Ordering::SeqCst);
+    let deadline = tokio::time::Instant::now() + STARTUP_GRACE;
+    while ready_count.load(Ordering::SeqCst) < total && tokio::time::Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+/// Connect every configured toy up front (or a `NullToy` per toy under
+/// `--simulate`), spawn one independent reconnect loop per sensor, and run a
+/// dispatcher that owns one `RangeMapper` per route and forwards mapped
+/// intensity to that route's toy(s). One sensor dropping out only tears down
+/// its own route; the others keep running.
+pub async fn run(config: &Config, args: &Args, running: &Arc<AtomicBool>) -> anyhow::Result<()> {
+    // 1. Connect every toy up front
+    let mut toys: HashMap<String, ToyOutput> = HashMap::new();
+    for toy_cfg in &config.toys {
+        let toy = if args.simulate {
+            ToyOutput::Null(NullToy::new())
+        } else {
+            let mut t = ToyController::connect(&toy_cfg.buttplug).await?;
+            t.find_device(toy_cfg.buttplug.device_index).await?;
+            ToyOutput::Real(t)
+        };
+        info!("Toy '{}' ready", toy_cfg.id);
+        toys.insert(toy_cfg.id.clone(), toy);
+    }
+
+    // 2. One RangeMapper per route, grouped by the sensor that feeds it
+    let mut routes_by_sensor: HashMap<String, Vec<(RangeMapper, Vec<String>)>> = HashMap::new();
+    for route in &config.routes {
+        let sensor = config
+            .sensors
+            .iter()
+            .find(|s| s.id == route.sensor_id)
+            .ok_or_else(|| anyhow::anyhow!("route references unknown sensor '{}'", route.sensor_id))?;
+        routes_by_sensor
+            .entry(route.sensor_id.clone())
+            .or_default()
+            .push((RangeMapper::new(sensor.mapping.clone()), route.toy_ids.clone()));
+    }
+
+    // 3. Spawn one independent reconnect loop per sensor, tagging every event
+    //    with its sensor id into a shared channel. A bounded startup grace
+    //    period lets sensors' streams land close together without letting one
+    //    unreachable sensor wedge the others indefinitely.
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let ready_count = Arc::new(AtomicUsize::new(0));
+    let total_sensors = config.sensors.len().max(1);
+    let mut sensor_handles = Vec::new();
+    for sensor in &config.sensors {
+        let sensor = sensor.clone();
+        let tx = tx.clone();
+        let running = running.clone();
+        let ready_count = ready_count.clone();
+        let simulate = args.simulate;
+        let simulate_rate_hz = args.simulate_rate_hz;
+        let simulate_replay = args.simulate_replay.clone();
+        let push_config = args.push_config;
+        sensor_handles.push(tokio::spawn(run_sensor(
+            sensor,
+            simulate,
+            simulate_rate_hz,
+            simulate_replay,
+            push_config,
+            running,
+            tx,
+            ready_count,
+            total_sensors,
+        )));
+    }
+    drop(tx);
+
+    // 4. Forward mapped intensity to each route's toy(s) until shutdown
+    info!(
+        "Dispatcher running — streaming from {} sensor(s) to {} toy(s)",
+        config.sensors.len(),
+        config.toys.len()
+    );
+    while running.load(Ordering::SeqCst) {
+        tokio::select! {
+            Some(tagged) = rx.recv() => {
+                let TaggedEvent { sensor_id, event } = tagged;
+                match event {
+                    BleEvent::RangeUpdate(distance_mm) => {
+                        if let Some(routes) = routes_by_sensor.get_mut(&sensor_id) {
+                            for (mapper, toy_ids) in routes.iter_mut() {
+                                let intensity = mapper.map(distance_mm);
+                                for toy_id in toy_ids {
+                                    if let Some(toy) = toys.get_mut(toy_id) {
+                                        if let Err(e) = toy.set_intensity(intensity).await {
+                                            warn!(
+                                                "Route {} -> {}: failed to set intensity: {:#}",
+                                                sensor_id, toy_id, e
+                                            );
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    BleEvent::Disconnected => {
+                        warn!("Sensor '{}' disconnected; its reconnect loop will retry", sensor_id);
+                    }
+                    BleEvent::Connected => {
+                        info!("Sensor '{}' connected", sensor_id);
+                    }
+                }
+            }
+            _ = tokio::time::sleep(Duration::from_secs(1)) => {
+                if !toys.values().any(|t| t.is_connected()) {
+                    warn!("Lost connection to every toy");
+                    break;
+                }
+            }
+        }
+    }
+
+    // Cleanup
+    info!("Stopping all toys...");
+    for (id, toy) in toys.iter_mut() {
+        if let Err(e) = toy.stop().await {
+            warn!("Toy '{}': failed to stop: {:#}", id, e);
+        }
+        let _ = toy.disconnect().await;
+    }
+    for handle in sensor_handles {
+        handle.abort();
+    }
+
+    Ok(())
+}
+
+/// Independent reconnect loop for one sensor: keeps retrying `find_device` /
+/// `run_ble_client` (or the simulated source under `--simulate`) until
+/// `running` goes false, tagging every event with this sensor's id. A
+/// disconnect here never tears down any other sensor's loop.
+async fn run_sensor(
+    sensor: SensorConfig,
+    simulate: bool,
+    simulate_rate_hz: f64,
+    simulate_replay: Option<PathBuf>,
+    push_config: bool,
+    running: Arc<AtomicBool>,
+    tx: mpsc::UnboundedSender<TaggedEvent>,
+    ready_count: Arc<AtomicUsize>,
+    total_sensors: usize,
+) {
+    let mut cached_device_id: Option<btleplug::platform::PeripheralId> = None;
+    let mut startup_pending = true;
+
+    while running.load(Ordering::SeqCst) {
+        let (raw_tx, mut raw_rx) = mpsc::unbounded_channel();
+        let sensor_id = sensor.id.clone();
+        let forward_tx = tx.clone();
+        let forward_handle = tokio::spawn(async move {
+            while let Some(event) = raw_rx.recv().await {
+                let tagged = TaggedEvent {
+                    sensor_id: sensor_id.clone(),
+                    event,
+                };
+                if forward_tx.send(tagged).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let result = if simulate {
+            if startup_pending {
+                startup_pending = false;
+                wait_for_startup(&ready_count, total_sensors).await;
+            }
+            match &simulate_replay {
+                Some(path) => sim::run_replay(path, raw_tx).await,
+                None => sim::run_sweep(&sensor.mapping, simulate_rate_hz, raw_tx).await,
+            }
+        } else {
+            match ble::find_device(
+                &sensor.ble.device_name,
+                sensor.ble.scan_timeout_secs,
+                sensor.ble.device_address.as_deref(),
+                cached_device_id.as_ref(),
+            )
+            .await
+            {
+                Ok(peripheral) => {
+                    cached_device_id = Some(peripheral.id());
+                    if startup_pending {
+                        startup_pending = false;
+                        wait_for_startup(&ready_count, total_sensors).await;
+                    }
+                    let push_config = push_config.then(|| sensor.mapping.clone());
+                    ble::run_ble_client(&peripheral, raw_tx, push_config).await
+                }
+                Err(e) => Err(e),
+            }
+        };
+
+        forward_handle.abort();
+
+        if let Err(e) = result {
+            error!("Sensor '{}' error: {:#}", sensor.id, e);
+            // The cached id may point at a device that's gone or changed address;
+            // drop it so the next attempt falls back to a real scan instead of
+            // retrying the same stale id forever.
+            cached_device_id = None;
+        }
+
+        if !running.load(Ordering::SeqCst) {
+            break;
+        }
+
+        info!(
+            "Sensor '{}' reconnecting in {}s...",
+            sensor.id, sensor.ble.reconnect_delay_secs
+        );
+        tokio::time::sleep(Duration::from_secs(sensor.ble.reconnect_delay_secs)).await;
+    }
+}
@@ -1,23 +1,24 @@
 mod ble;
 mod config;
+mod dispatcher;
 mod mapper;
+mod sim;
 mod toy;
 
 use clap::Parser;
 use config::Config;
-use mapper::RangeMapper;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tokio::sync::mpsc;
 use tracing::{error, info, warn};
 
 #[derive(Parser, Debug)]
 #[command(
     name = "fancypants",
     about = "BLE rangefinder to Buttplug.io middleware",
-    long_about = "Connects to a fancypants-nrf52 BLE rangefinder and translates distance \
-                   readings into haptic intensity for toys via Intiface Engine."
+    long_about = "Connects any number of fancypants-nrf52 BLE rangefinders to any number of \
+                   toys via Intiface Engine, routing each sensor's distance readings to \
+                   haptic intensity for its configured toy(s)."
 )]
 struct Args {
     /// Path to TOML configuration file
@@ -31,8 +32,31 @@ struct Args {
     /// Log level (trace, debug, info, warn, error)
     #[arg(short, long, default_value = "info")]
     log_level: String,
+
+    /// Push each sensor's mapping config to its firmware over the RANGE_CONFIG
+    /// characteristic once connected, so sensor-side sampling/thresholds match
+    #[arg(long)]
+    push_config: bool,
+
+    /// Run the full pipeline without any hardware: feed a synthetic range source
+    /// into each sensor's mapper and log the toy commands that would be sent
+    /// instead of sending them to Intiface
+    #[arg(long)]
+    simulate: bool,
+
+    /// Update rate in Hz for the synthetic sweep used by `--simulate` (ignored if
+    /// `--simulate-replay` is set)
+    #[arg(long, default_value_t = 10.0)]
+    simulate_rate_hz: f64,
+
+    /// Replay `timestamp,distance_mm` samples from a CSV or JSONL file instead of
+    /// the synthetic sweep (only used with `--simulate`)
+    #[arg(long)]
+    simulate_replay: Option<PathBuf>,
 }
 
+const RETRY_DELAY_SECS: u64 = 5;
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let args = Args::parse();
@@ -61,17 +85,25 @@ async fn main() -> anyhow::Result<()> {
     };
 
     info!("Configuration loaded:");
-    info!("  BLE device: {}", config.ble.device_name);
-    info!(
-        "  Mapping: range [{}-{}mm] -> intensity [{}-{}], invert={}, deadzone={}mm",
-        config.mapping.min_range_mm,
-        config.mapping.max_range_mm,
-        config.mapping.min_intensity,
-        config.mapping.max_intensity,
-        config.mapping.invert,
-        config.mapping.deadzone_mm,
-    );
-    info!("  Buttplug server: {}", config.buttplug.server_address);
+    for sensor in &config.sensors {
+        info!(
+            "  Sensor '{}': {} -> range [{}-{}mm] -> intensity [{}-{}], invert={}, deadzone={}mm",
+            sensor.id,
+            sensor.ble.device_name,
+            sensor.mapping.min_range_mm,
+            sensor.mapping.max_range_mm,
+            sensor.mapping.min_intensity,
+            sensor.mapping.max_intensity,
+            sensor.mapping.invert,
+            sensor.mapping.deadzone_mm,
+        );
+    }
+    for toy in &config.toys {
+        info!("  Toy '{}': {}", toy.id, toy.buttplug.server_address);
+    }
+    for route in &config.routes {
+        info!("  Route: {} -> {:?}", route.sensor_id, route.toy_ids);
+    }
 
     // Ctrl+C handling
     let running = Arc::new(AtomicBool::new(true));
@@ -81,26 +113,21 @@ async fn main() -> anyhow::Result<()> {
         running_clone.store(false, Ordering::SeqCst);
     })?;
 
-    // Main loop with reconnection
+    // Each sensor reconnects independently inside the dispatcher; this outer loop
+    // only retries the dispatcher itself, e.g. if a toy connection never came up.
     while running.load(Ordering::SeqCst) {
-        match run_session(&config, &running).await {
+        match dispatcher::run(&config, &args, &running).await {
             Ok(()) => {
                 info!("Session ended cleanly");
                 break;
             }
             Err(e) => {
-                error!("Session error: {:#}", e);
+                error!("Dispatcher error: {:#}", e);
                 if !running.load(Ordering::SeqCst) {
                     break;
                 }
-                info!(
-                    "Reconnecting in {}s...",
-                    config.ble.reconnect_delay_secs
-                );
-                tokio::time::sleep(std::time::Duration::from_secs(
-                    config.ble.reconnect_delay_secs,
-                ))
-                .await;
+                info!("Retrying in {}s...", RETRY_DELAY_SECS);
+                tokio::time::sleep(std::time::Duration::from_secs(RETRY_DELAY_SECS)).await;
             }
         }
     }
@@ -108,69 +135,3 @@ async fn main() -> anyhow::Result<()> {
     info!("Goodbye");
     Ok(())
 }
-
-async fn run_session(config: &Config, running: &Arc<AtomicBool>) -> anyhow::Result<()> {
-    // 1. Find fancypants-nrf52 BLE device
-    let peripheral: btleplug::platform::Peripheral =
-        ble::find_device(&config.ble.device_name, config.ble.scan_timeout_secs).await?;
-
-    // 2. Connect to Intiface Engine
-    let mut toy: toy::ToyController =
-        toy::ToyController::connect(&config.buttplug.server_address).await?;
-    toy.find_device(config.buttplug.device_index).await?;
-
-    // 3. Set up range mapper
-    let mut mapper = RangeMapper::new(config.mapping.clone());
-
-    // 4. Start BLE notification listener
-    let (tx, mut rx) = mpsc::unbounded_channel();
-    let ble_handle = {
-        let peripheral = peripheral.clone();
-        let tx = tx.clone();
-        tokio::spawn(async move {
-            if let Err(e) = ble::run_ble_client(&peripheral, tx).await {
-                error!("BLE client error: {:#}", e);
-            }
-        })
-    };
-
-    // 5. Process range updates and drive toy
-    info!("Running â€” move your hand near the sensor!");
-
-    while running.load(Ordering::SeqCst) {
-        tokio::select! {
-            Some(event) = rx.recv() => {
-                match event {
-                    ble::BleEvent::RangeUpdate(distance_mm) => {
-                        let intensity = mapper.map(distance_mm);
-                        if let Err(e) = toy.set_intensity(intensity).await {
-                            warn!("Failed to set intensity: {:#}", e);
-                        }
-                    }
-                    ble::BleEvent::Disconnected => {
-                        warn!("BLE disconnected");
-                        break;
-                    }
-                    ble::BleEvent::Connected => {
-                        info!("BLE connected");
-                    }
-                }
-            }
-            _ = tokio::time::sleep(std::time::Duration::from_secs(1)) => {
-                // Periodic check that everything is still alive
-                if !toy.is_connected() {
-                    warn!("Lost connection to Intiface");
-                    break;
-                }
-            }
-        }
-    }
-
-    // Cleanup
-    info!("Stopping device...");
-    let _ = toy.stop().await;
-    let _ = toy.disconnect().await;
-    ble_handle.abort();
-
-    Ok(())
-}
@@ -85,6 +85,7 @@ mod tests {
             max_intensity: 1.0,
             deadzone_mm: 500,
             smoothing: 0.0, // disable for unit tests
+            sample_rate_hz: 20,
         }
     }
 
@@ -0,0 +1,156 @@
+use crate::ble::BleEvent;
+use crate::config::MappingConfig;
+use std::path::Path;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{debug, info};
+
+/// Emit a synthetic triangle-wave sweep between `min_range_mm` and `max_range_mm`,
+/// the way buttplug's test device comm manager lets you exercise the stack without
+/// any real hardware attached.
+pub async fn run_sweep(
+    mapping: &MappingConfig,
+    rate_hz: f64,
+    tx: mpsc::UnboundedSender<BleEvent>,
+) -> anyhow::Result<()> {
+    tx.send(BleEvent::Connected)?;
+
+    let min = mapping.min_range_mm as f64;
+    let max = mapping.max_range_mm as f64;
+    let span = (max - min).max(1.0);
+    let step_secs = 1.0 / rate_hz.max(0.1);
+    let period_secs = 4.0; // one full sweep up to max_range_mm and back down
+
+    let mut elapsed = 0.0;
+    loop {
+        let phase = (elapsed % period_secs) / period_secs;
+        let triangle = if phase < 0.5 { phase * 2.0 } else { 2.0 - phase * 2.0 };
+        let distance_mm = (min + triangle * span) as u16;
+
+        if tx.send(BleEvent::RangeUpdate(distance_mm)).is_err() {
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_secs_f64(step_secs)).await;
+        elapsed += step_secs;
+    }
+
+    let _ = tx.send(BleEvent::Disconnected);
+    Ok(())
+}
+
+/// Replay `timestamp,distance_mm` samples from a CSV or JSONL file, sending one
+/// `BleEvent::RangeUpdate` per line and pacing sends to match the recorded
+/// timestamp deltas. If the first non-empty line doesn't parse as a sample, it's
+/// treated as a CSV header row and skipped rather than failing the replay.
+pub async fn run_replay(path: &Path, tx: mpsc::UnboundedSender<BleEvent>) -> anyhow::Result<()> {
+    let content = std::fs::read_to_string(path)?;
+    tx.send(BleEvent::Connected)?;
+
+    let mut last_timestamp: Option<f64> = None;
+    let mut first_line = true;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let sample = parse_sample(line);
+        if first_line {
+            first_line = false;
+            if let Err(e) = &sample {
+                debug!("Skipping unparsable first line as a header ({:#}): {}", e, line);
+                continue;
+            }
+        }
+        let (timestamp, distance_mm) = sample?;
+
+        if let Some(prev) = last_timestamp {
+            let delta = (timestamp - prev).max(0.0);
+            if delta > 0.0 {
+                tokio::time::sleep(Duration::from_secs_f64(delta)).await;
+            }
+        }
+        last_timestamp = Some(timestamp);
+
+        if tx.send(BleEvent::RangeUpdate(distance_mm)).is_err() {
+            break;
+        }
+    }
+
+    info!("Replay of {:?} finished", path);
+    let _ = tx.send(BleEvent::Disconnected);
+    Ok(())
+}
+
+fn parse_sample(line: &str) -> anyhow::Result<(f64, u16)> {
+    if line.starts_with('{') {
+        let value: serde_json::Value = serde_json::from_str(line)?;
+        let timestamp = value["timestamp"]
+            .as_f64()
+            .ok_or_else(|| anyhow::anyhow!("sample missing 'timestamp' field"))?;
+        let distance_mm = value["distance_mm"]
+            .as_u64()
+            .ok_or_else(|| anyhow::anyhow!("sample missing 'distance_mm' field"))?;
+        return Ok((timestamp, distance_mm as u16));
+    }
+
+    let mut parts = line.splitn(2, ',');
+    let timestamp: f64 = parts
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("sample missing timestamp column"))?
+        .trim()
+        .parse()?;
+    let distance_mm: u16 = parts
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("sample missing distance_mm column"))?
+        .trim()
+        .parse()?;
+    Ok((timestamp, distance_mm))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sample_csv() {
+        let (timestamp, distance_mm) = parse_sample("1.5,120").unwrap();
+        assert_eq!(timestamp, 1.5);
+        assert_eq!(distance_mm, 120);
+    }
+
+    #[test]
+    fn test_parse_sample_jsonl() {
+        let (timestamp, distance_mm) =
+            parse_sample(r#"{"timestamp": 2.25, "distance_mm": 80}"#).unwrap();
+        assert_eq!(timestamp, 2.25);
+        assert_eq!(distance_mm, 80);
+    }
+
+    #[test]
+    fn test_parse_sample_csv_missing_column() {
+        assert!(parse_sample("1.5").is_err());
+    }
+
+    #[test]
+    fn test_parse_sample_csv_malformed_number() {
+        assert!(parse_sample("not_a_number,120").is_err());
+    }
+
+    #[test]
+    fn test_parse_sample_jsonl_missing_field() {
+        assert!(parse_sample(r#"{"timestamp": 2.25}"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_sample_jsonl_malformed() {
+        assert!(parse_sample(r#"{"timestamp": "oops""#).is_err());
+    }
+
+    #[test]
+    fn test_parse_sample_header_row_is_unparsable() {
+        // `run_replay` relies on this failing so it can skip the row as a header.
+        assert!(parse_sample("timestamp,distance_mm").is_err());
+    }
+}
@@ -1,4 +1,8 @@
-use buttplug::client::{device::ScalarValueCommand, ButtplugClient, ButtplugClientDevice};
+use crate::config::ButtplugConfig;
+use buttplug::client::{
+    device::{LinearCommand, RotateCommand, ScalarValueCommand},
+    ButtplugClient, ButtplugClientDevice,
+};
 use buttplug::core::connector::new_json_ws_client_connector;
 use buttplug::core::message::ActuatorType;
 use std::sync::Arc;
@@ -9,23 +13,29 @@ use tracing::{debug, info};
 pub struct ToyController {
     client: ButtplugClient,
     target_device: Option<Arc<ButtplugClientDevice>>,
+    config: ButtplugConfig,
     last_intensity: f64,
+    /// Flips on every "Linear" update so consecutive strokes alternate between
+    /// the mapped value and the baseline instead of holding position
+    stroke_toggle: bool,
 }
 
 impl ToyController {
     /// Connect to Intiface Engine via websocket
-    pub async fn connect(server_address: &str) -> anyhow::Result<Self> {
+    pub async fn connect(config: &ButtplugConfig) -> anyhow::Result<Self> {
         let client = ButtplugClient::new("Fancypants");
 
-        let connector = new_json_ws_client_connector(server_address);
+        let connector = new_json_ws_client_connector(&config.server_address);
 
         client.connect(connector).await?;
-        info!("Connected to Intiface Engine at {}", server_address);
+        info!("Connected to Intiface Engine at {}", config.server_address);
 
         Ok(ToyController {
             client,
             target_device: None,
+            config: config.clone(),
             last_intensity: 0.0,
+            stroke_toggle: false,
         })
     }
 
@@ -50,17 +60,14 @@ impl ToyController {
                 .ok_or_else(|| anyhow::anyhow!("Device index {} not found", idx))?
                 .clone()
         } else {
-            // Use first device with vibrate capability
+            // Use first device supporting any of the configured actuator types
             devices
                 .iter()
                 .find(|d| {
-                    d.message_attributes()
-                        .scalar_cmd()
-                        .as_ref()
-                        .map(|attrs| {
-                            attrs.iter().any(|a| *a.actuator_type() == ActuatorType::Vibrate)
-                        })
-                        .unwrap_or(false)
+                    self.config
+                        .actuator_types
+                        .iter()
+                        .any(|t| device_supports(d, t))
                 })
                 .or_else(|| devices.first())
                 .ok_or_else(|| anyhow::anyhow!("No suitable device found"))?
@@ -76,27 +83,52 @@ impl ToyController {
         Ok(())
     }
 
-    /// Set vibration intensity (0.0 - 1.0) on the target device.
-    /// Skips the command if intensity hasn't changed significantly.
+    /// Set output intensity (0.0 - 1.0) on the target device, fanning the single
+    /// mapped value out to every actuator type configured in `actuator_types`.
+    ///
+    /// Vibrate/Rotate skip the command if intensity hasn't changed significantly,
+    /// since holding a scalar value is a no-op. Linear ignores that skip: a
+    /// stroker needs to keep reciprocating even under constant input, so it always
+    /// alternates between the mapped value and the baseline on every call.
     pub async fn set_intensity(&mut self, intensity: f64) -> anyhow::Result<()> {
         let device = self
             .target_device
             .as_ref()
-            .ok_or_else(|| anyhow::anyhow!("No target device"))?;
-
-        // Skip if change is negligible (< 1% difference)
-        if (intensity - self.last_intensity).abs() < 0.01 {
-            return Ok(());
-        }
+            .ok_or_else(|| anyhow::anyhow!("No target device"))?
+            .clone();
 
         let clamped = intensity.clamp(0.0, 1.0);
-        debug!("Setting intensity: {:.3}", clamped);
-
-        device
-            .vibrate(&ScalarValueCommand::ScalarValue(clamped))
-            .await?;
+        let changed = (clamped - self.last_intensity).abs() >= 0.01;
+
+        for actuator_type in &self.config.actuator_types.clone() {
+            match actuator_type.as_str() {
+                "Linear" => {
+                    let position = if self.stroke_toggle { 0.0 } else { clamped };
+                    self.stroke_toggle = !self.stroke_toggle;
+                    debug!("Setting linear position: {:.3}", position);
+                    device
+                        .linear(&LinearCommand::Linear(self.config.stroke_duration_ms, position))
+                        .await?;
+                }
+                "Rotate" if changed => {
+                    debug!("Setting rotation speed: {:.3}", clamped);
+                    device
+                        .rotate(&RotateCommand::Rotate(clamped, self.config.rotation_clockwise))
+                        .await?;
+                }
+                _ if changed => {
+                    debug!("Setting intensity: {:.3}", clamped);
+                    device
+                        .vibrate(&ScalarValueCommand::ScalarValue(clamped))
+                        .await?;
+                }
+                _ => {}
+            }
+        }
 
-        self.last_intensity = clamped;
+        if changed {
+            self.last_intensity = clamped;
+        }
         Ok(())
     }
 
@@ -105,6 +137,7 @@ impl ToyController {
         if let Some(device) = &self.target_device {
             device.stop().await?;
             self.last_intensity = 0.0;
+            self.stroke_toggle = false;
         }
         Ok(())
     }
@@ -120,3 +153,91 @@ impl ToyController {
         self.client.connected()
     }
 }
+
+/// Does `device` expose the buttplug command matching `actuator_type` ("Vibrate",
+/// "Linear", or "Rotate")?
+fn device_supports(device: &ButtplugClientDevice, actuator_type: &str) -> bool {
+    let attrs = device.message_attributes();
+    match actuator_type {
+        "Linear" => attrs.linear_cmd().is_some(),
+        "Rotate" => attrs.rotate_cmd().is_some(),
+        _ => attrs
+            .scalar_cmd()
+            .as_ref()
+            .map(|cmds| cmds.iter().any(|a| *a.actuator_type() == ActuatorType::Vibrate))
+            .unwrap_or(false),
+    }
+}
+
+/// No-op toy that logs the commands it would send instead of talking to Intiface.
+/// Used by `--simulate` to exercise mapping/smoothing/deadzone behavior without a
+/// toy present.
+pub struct NullToy {
+    last_intensity: f64,
+}
+
+impl NullToy {
+    pub fn new() -> Self {
+        NullToy { last_intensity: 0.0 }
+    }
+
+    pub async fn set_intensity(&mut self, intensity: f64) -> anyhow::Result<()> {
+        let clamped = intensity.clamp(0.0, 1.0);
+        if (clamped - self.last_intensity).abs() >= 0.01 {
+            info!("[simulate] would set intensity: {:.3}", clamped);
+            self.last_intensity = clamped;
+        }
+        Ok(())
+    }
+
+    pub async fn stop(&mut self) -> anyhow::Result<()> {
+        info!("[simulate] would stop device");
+        self.last_intensity = 0.0;
+        Ok(())
+    }
+
+    pub async fn disconnect(&self) -> anyhow::Result<()> {
+        info!("[simulate] would disconnect");
+        Ok(())
+    }
+
+    pub fn is_connected(&self) -> bool {
+        true
+    }
+}
+
+/// Either a real Buttplug-connected toy or a `NullToy` standing in for `--simulate`.
+pub enum ToyOutput {
+    Real(ToyController),
+    Null(NullToy),
+}
+
+impl ToyOutput {
+    pub async fn set_intensity(&mut self, intensity: f64) -> anyhow::Result<()> {
+        match self {
+            ToyOutput::Real(t) => t.set_intensity(intensity).await,
+            ToyOutput::Null(t) => t.set_intensity(intensity).await,
+        }
+    }
+
+    pub async fn stop(&mut self) -> anyhow::Result<()> {
+        match self {
+            ToyOutput::Real(t) => t.stop().await,
+            ToyOutput::Null(t) => t.stop().await,
+        }
+    }
+
+    pub async fn disconnect(&self) -> anyhow::Result<()> {
+        match self {
+            ToyOutput::Real(t) => t.disconnect().await,
+            ToyOutput::Null(t) => t.disconnect().await,
+        }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        match self {
+            ToyOutput::Real(t) => t.is_connected(),
+            ToyOutput::Null(t) => t.is_connected(),
+        }
+    }
+}